@@ -0,0 +1,268 @@
+//! A minimal, single-future executor for `no_std` targets
+//!
+//! See [`block_on`].
+
+use core::task::{Context, RawWaker, RawWakerVTable, Waker};
+
+/// Drive `future` to completion on the current core, yielding to `idle`
+/// between polls
+///
+/// This replaces a `loop { poll() }` busy-wait with one that gives the
+/// caller a chance to put the core to sleep (typically via
+/// [`cortex_m::asm::wfi`]) whenever the operation isn't ready yet, instead
+/// of spinning at 100% CPU. `idle` is called every time a poll returns
+/// pending; with an interrupt-driven timer, the next poll after `idle`
+/// returns will typically make progress.
+///
+/// Works both with this crate's manual, `poll`-based futures (such as
+/// [`StepFuture`]) and, when the `async` feature is enabled, with any
+/// `F: core::future::Future + Unpin`.
+///
+/// [`cortex_m::asm::wfi`]: https://docs.rs/cortex-m/latest/cortex_m/asm/fn.wfi.html
+/// [`StepFuture`]: crate::stepper::step::StepFuture
+pub fn block_on<T>(mut task: T, mut idle: impl FnMut()) -> T::Output
+where
+    T: Task,
+{
+    let waker = noop_waker();
+
+    loop {
+        match task.poll_once(&waker) {
+            core::task::Poll::Ready(output) => return output,
+            core::task::Poll::Pending => idle(),
+        }
+    }
+}
+
+/// Something [`block_on`] can drive to completion
+///
+/// This exists so [`block_on`] can be generic over both this crate's
+/// manual, `poll`-based futures and, behind the `async` feature, any
+/// `core::future::Future`, without the latter needing a real executor.
+pub trait Task {
+    /// The value produced once the task is done
+    type Output;
+
+    /// Poll the task once, registering `waker` if it isn't done yet
+    fn poll_once(&mut self, waker: &Waker) -> core::task::Poll<Self::Output>;
+}
+
+#[cfg(not(feature = "async"))]
+mod manual {
+    use core::convert::TryFrom;
+    use core::task::Waker;
+
+    use embedded_hal::digital::ErrorType;
+    use embedded_time::duration::Nanoseconds;
+
+    use crate::stepper::move_to::MoveToFuture;
+    use crate::stepper::set_step_mode::SetStepModeFuture;
+    use crate::stepper::step::StepFuture;
+    use crate::stepper::{Error, SignalError};
+    use crate::traits::{MotionControl, SetStepMode, Step};
+
+    use super::Task;
+
+    // When the `async` feature is enabled, these types implement
+    // `core::future::Future` themselves, and the blanket impl below covers
+    // them instead. Implementing `Task` here too would conflict with that
+    // blanket impl, so these are only provided without `async`.
+
+    impl<Driver, Timer, const TIMER_HZ: u32> Task for StepFuture<Driver, Timer, TIMER_HZ>
+    where
+        Driver: Step,
+        Timer: fugit_timer::Timer<TIMER_HZ>,
+    {
+        type Output = Result<
+            (),
+            SignalError<Driver::Error, <Driver::Step as ErrorType>::Error, Timer::Error>,
+        >;
+
+        fn poll_once(&mut self, _waker: &Waker) -> core::task::Poll<Self::Output> {
+            self.poll()
+        }
+    }
+
+    impl<Driver> Task for MoveToFuture<Driver>
+    where
+        Driver: MotionControl,
+    {
+        type Output = Result<(), Driver::Error>;
+
+        fn poll_once(&mut self, _waker: &Waker) -> core::task::Poll<Self::Output> {
+            self.poll()
+        }
+    }
+
+    impl<'r, Driver, Timer> Task for SetStepModeFuture<'r, Driver, Timer>
+    where
+        Driver: SetStepMode,
+        Timer: embedded_hal::timer::CountDown,
+        Timer::Time: TryFrom<Nanoseconds>,
+    {
+        type Output = Result<
+            (),
+            Error<Driver::Error, <Timer::Time as TryFrom<Nanoseconds>>::Error, Timer::Error>,
+        >;
+
+        fn poll_once(&mut self, _waker: &Waker) -> core::task::Poll<Self::Output> {
+            self.poll()
+        }
+    }
+
+    // `Timeout` only implements `core::future::Future` under the `async`
+    // feature too, so it follows the same rule as the three types above.
+    impl<F, Timer, const TIMER_HZ: u32> Task
+        for crate::stepper::timeout::Timeout<F, Timer, TIMER_HZ>
+    where
+        F: crate::stepper::timeout::Pollable,
+        Timer: fugit_timer::Timer<TIMER_HZ>,
+    {
+        type Output =
+            Result<(), crate::stepper::timeout::TimeoutError<F::Error, Timer::Error>>;
+
+        fn poll_once(&mut self, _waker: &Waker) -> core::task::Poll<Self::Output> {
+            self.poll()
+        }
+    }
+}
+
+// `JoinAll` and `SelectAll` never implement `core::future::Future`
+// themselves, so there's no blanket-impl conflict to avoid: these are
+// available regardless of the `async` feature.
+
+impl<F, const N: usize> Task for crate::stepper::join::JoinAll<F, N>
+where
+    F: crate::stepper::timeout::Pollable,
+{
+    type Output = [Result<(), F::Error>; N];
+
+    fn poll_once(&mut self, _waker: &Waker) -> core::task::Poll<Self::Output> {
+        self.poll()
+    }
+}
+
+impl<F, const N: usize> Task for crate::stepper::join::SelectAll<F, N>
+where
+    F: crate::stepper::timeout::Pollable,
+{
+    type Output = (usize, Result<(), F::Error>);
+
+    fn poll_once(&mut self, _waker: &Waker) -> core::task::Poll<Self::Output> {
+        self.poll()
+    }
+}
+
+// Same story for the heterogeneous, tuple-based Join/Select combinators.
+
+macro_rules! impl_task_for_heterogeneous_join {
+    ($name:ident, $($F:ident),+) => {
+        impl<$($F),+> Task for crate::stepper::join::$name<$($F),+>
+        where
+            $($F: crate::stepper::timeout::Pollable),+
+        {
+            type Output = ($(Result<(), $F::Error>,)+);
+
+            fn poll_once(&mut self, _waker: &Waker) -> core::task::Poll<Self::Output> {
+                self.poll()
+            }
+        }
+    };
+}
+
+impl_task_for_heterogeneous_join!(Join2, F0, F1);
+impl_task_for_heterogeneous_join!(Join3, F0, F1, F2);
+impl_task_for_heterogeneous_join!(Join4, F0, F1, F2, F3);
+
+macro_rules! impl_task_for_heterogeneous_select {
+    ($name:ident, $result:ident, $($F:ident),+) => {
+        impl<$($F),+> Task for crate::stepper::join::$name<$($F),+>
+        where
+            $($F: crate::stepper::timeout::Pollable),+
+        {
+            type Output = crate::stepper::join::$result<$($F),+>;
+
+            fn poll_once(&mut self, _waker: &Waker) -> core::task::Poll<Self::Output> {
+                self.poll()
+            }
+        }
+    };
+}
+
+impl_task_for_heterogeneous_select!(Select2, Select2Result, F0, F1);
+impl_task_for_heterogeneous_select!(Select3, Select3Result, F0, F1, F2);
+impl_task_for_heterogeneous_select!(Select4, Select4Result, F0, F1, F2, F3);
+
+#[cfg(feature = "async")]
+impl<F> Task for F
+where
+    F: core::future::Future + Unpin,
+{
+    type Output = F::Output;
+
+    fn poll_once(&mut self, waker: &Waker) -> core::task::Poll<Self::Output> {
+        let mut cx = Context::from_waker(waker);
+        core::pin::Pin::new(self).poll(&mut cx)
+    }
+}
+
+/// Build a [`Waker`] that does nothing when woken
+///
+/// [`block_on`] re-polls in a loop regardless of wakeups, so it has no use
+/// for a real waker; it still needs to hand one to `Future::poll`, since
+/// that's part of the `Future` contract.
+fn noop_waker() -> Waker {
+    fn no_op(_: *const ()) {}
+
+    fn clone(_: *const ()) -> RawWaker {
+        raw_waker()
+    }
+
+    fn raw_waker() -> RawWaker {
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, no_op, no_op, no_op);
+        RawWaker::new(core::ptr::null(), &VTABLE)
+    }
+
+    unsafe { Waker::from_raw(raw_waker()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A task that reports pending `pending_polls` times before resolving
+    struct CountingTask {
+        pending_polls: u32,
+    }
+
+    impl Task for CountingTask {
+        type Output = ();
+
+        fn poll_once(&mut self, _waker: &Waker) -> core::task::Poll<Self::Output> {
+            if self.pending_polls == 0 {
+                core::task::Poll::Ready(())
+            } else {
+                self.pending_polls -= 1;
+                core::task::Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn block_on_returns_without_idling_when_already_ready() {
+        let mut idle_calls = 0;
+
+        block_on(CountingTask { pending_polls: 0 }, || idle_calls += 1);
+
+        assert_eq!(idle_calls, 0);
+    }
+
+    #[test]
+    fn block_on_idles_exactly_once_per_pending_poll() {
+        let mut idle_calls = 0;
+
+        block_on(CountingTask { pending_polls: 3 }, || idle_calls += 1);
+
+        assert_eq!(idle_calls, 3);
+    }
+}
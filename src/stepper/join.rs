@@ -0,0 +1,468 @@
+//! Combinators for driving several axes at once
+//!
+//! CNC- and 3D-printer-style moves need several axes to reach their
+//! targets "simultaneously". Every combinator in this module drives a
+//! fixed number of [`Pollable`] futures together, without pulling in an
+//! external async runtime. Each inner future already advances its own
+//! driver's state machine independently on `poll`, so the combinators
+//! only need to fan out `poll` calls across the set and track which
+//! indices are done.
+//!
+//! [`JoinAll`] and [`SelectAll`] work over a homogeneous, fixed-size array
+//! of futures (same `Driver`/`Timer` types), which is the natural shape
+//! for a set of identical axes. [`Join2`]/[`Join3`]/[`Join4`] and
+//! [`Select2`]/[`Select3`]/[`Select4`] cover the heterogeneous case — an
+//! X axis on one timer and a Y axis on another, say — by driving a tuple
+//! of differently-typed futures instead.
+//!
+//! [`MoveToFuture`]: super::move_to::MoveToFuture
+
+use core::task::Poll;
+
+use super::timeout::Pollable;
+
+/// Drives `N` futures together, resolving once every one of them has
+/// finished
+///
+/// Returns [`Poll::Pending`] from [`Self::poll`] while any of the futures
+/// is still running, and [`Poll::Ready`] with a per-index result array
+/// once all of them have finished.
+#[must_use]
+pub struct JoinAll<F: Pollable, const N: usize> {
+    futures: [F; N],
+    results: [Option<Result<(), F::Error>>; N],
+}
+
+impl<F, const N: usize> JoinAll<F, N>
+where
+    F: Pollable,
+{
+    /// Create a new `JoinAll`, driving all of `futures` together
+    pub fn new(futures: [F; N]) -> Self {
+        Self {
+            futures,
+            results: core::array::from_fn(|_| None),
+        }
+    }
+
+    /// Poll every inner future that hasn't finished yet
+    ///
+    /// The result of a future is cached as soon as it is produced, so
+    /// that a future that finished early isn't polled again while the
+    /// others are still catching up.
+    pub fn poll(&mut self) -> Poll<[Result<(), F::Error>; N]> {
+        for (future, result) in self.futures.iter_mut().zip(self.results.iter_mut()) {
+            if result.is_none() {
+                if let Poll::Ready(output) = future.poll() {
+                    *result = Some(output);
+                }
+            }
+        }
+
+        if self.results.iter().all(Option::is_some) {
+            Poll::Ready(core::array::from_fn(|i| self.results[i].take().unwrap()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    /// Wait until every future has finished
+    ///
+    /// This method will call [`Self::poll`] in a busy loop until all
+    /// futures have finished.
+    pub fn wait(&mut self) -> [Result<(), F::Error>; N] {
+        loop {
+            if let Poll::Ready(results) = self.poll() {
+                return results;
+            }
+        }
+    }
+
+    /// Drop the futures and release the resources that were moved into
+    /// each of them
+    pub fn release(self) -> [F::Released; N] {
+        self.futures.map(Pollable::release)
+    }
+}
+
+/// Drives `N` futures together, resolving as soon as the first one
+/// finishes
+///
+/// Useful for "stop when any limit switch-bound move completes"-style
+/// use cases. The remaining futures are left exactly as they were and
+/// keep running; call [`Self::poll`] again to wait for the next one, or
+/// [`Self::release`] to recover all of the drivers.
+#[must_use]
+pub struct SelectAll<F, const N: usize> {
+    futures: [F; N],
+    done: [bool; N],
+}
+
+impl<F, const N: usize> SelectAll<F, N>
+where
+    F: Pollable,
+{
+    /// Create a new `SelectAll`, driving all of `futures` together
+    pub fn new(futures: [F; N]) -> Self {
+        Self {
+            futures,
+            done: [false; N],
+        }
+    }
+
+    /// Poll every inner future that hasn't finished yet, in index order,
+    /// returning the index and result of the first one found to have
+    /// finished
+    ///
+    /// Once an index has resolved, it is skipped on every subsequent call,
+    /// so repeated polling reports each axis at most once, in the order
+    /// they actually finish.
+    pub fn poll(&mut self) -> Poll<(usize, Result<(), F::Error>)> {
+        for (index, future) in self.futures.iter_mut().enumerate() {
+            if self.done[index] {
+                continue;
+            }
+
+            if let Poll::Ready(result) = future.poll() {
+                self.done[index] = true;
+                return Poll::Ready((index, result));
+            }
+        }
+
+        Poll::Pending
+    }
+
+    /// Wait until the first future finishes
+    ///
+    /// This method will call [`Self::poll`] in a busy loop until one of
+    /// the futures has finished.
+    pub fn wait(&mut self) -> (usize, Result<(), F::Error>) {
+        loop {
+            if let Poll::Ready(result) = self.poll() {
+                return result;
+            }
+        }
+    }
+
+    /// Drop the futures and release the resources that were moved into
+    /// each of them
+    pub fn release(self) -> [F::Released; N] {
+        self.futures.map(Pollable::release)
+    }
+}
+
+/// Generates a `JoinN` type that drives a tuple of `N` differently-typed
+/// [`Pollable`] futures together, resolving once every one of them has
+/// finished. Mirrors [`JoinAll`], but over a tuple instead of an array, so
+/// each axis can have its own `Driver`/`Timer` type.
+macro_rules! impl_heterogeneous_join {
+    ($name:ident, $(($F:ident, $idx:tt)),+) => {
+        #[must_use]
+        #[doc = concat!(
+            "Drives a tuple of differently-typed futures together, ",
+            "resolving once every one of them has finished. ",
+            "See the [module-level documentation](self) for how this ",
+            "relates to [`JoinAll`]."
+        )]
+        pub struct $name<$($F: Pollable),+> {
+            futures: ($($F,)+),
+            results: ($(Option<Result<(), $F::Error>>,)+),
+        }
+
+        impl<$($F: Pollable),+> $name<$($F),+> {
+            #[doc = concat!("Create a new `", stringify!($name), "`, driving all of `futures` together")]
+            pub fn new(futures: ($($F,)+)) -> Self {
+                Self {
+                    futures,
+                    results: ($({ let _ = $idx; None },)+),
+                }
+            }
+
+            /// Poll every inner future that hasn't finished yet
+            pub fn poll(&mut self) -> Poll<($(Result<(), $F::Error>,)+)> {
+                $(
+                    if self.results.$idx.is_none() {
+                        if let Poll::Ready(output) = self.futures.$idx.poll() {
+                            self.results.$idx = Some(output);
+                        }
+                    }
+                )+
+
+                if $(self.results.$idx.is_some())&&+ {
+                    Poll::Ready(($(self.results.$idx.take().unwrap(),)+))
+                } else {
+                    Poll::Pending
+                }
+            }
+
+            /// Wait until every future has finished
+            ///
+            /// This method will call [`Self::poll`] in a busy loop until
+            /// all futures have finished.
+            pub fn wait(&mut self) -> ($(Result<(), $F::Error>,)+) {
+                loop {
+                    if let Poll::Ready(results) = self.poll() {
+                        return results;
+                    }
+                }
+            }
+
+            /// Drop the futures and release the resources that were
+            /// moved into each of them
+            pub fn release(self) -> ($($F::Released,)+) {
+                ($(self.futures.$idx.release(),)+)
+            }
+        }
+    };
+}
+
+impl_heterogeneous_join!(Join2, (F0, 0), (F1, 1));
+impl_heterogeneous_join!(Join3, (F0, 0), (F1, 1), (F2, 2));
+impl_heterogeneous_join!(Join4, (F0, 0), (F1, 1), (F2, 2), (F3, 3));
+
+/// Generates a `SelectN` type (and its matching `SelectNResult` enum) that
+/// drives a tuple of `N` differently-typed [`Pollable`] futures together,
+/// resolving as soon as the first one finishes. Mirrors [`SelectAll`], but
+/// over a tuple instead of an array, so each axis can have its own
+/// `Driver`/`Timer` type.
+macro_rules! impl_heterogeneous_select {
+    ($name:ident, $result:ident, $(($F:ident, $idx:tt, $variant:ident)),+) => {
+        #[doc = concat!(
+            "The result of a [`", stringify!($name), "`], tagging which ",
+            "axis finished first"
+        )]
+        #[derive(Debug, PartialEq)]
+        pub enum $result<$($F: Pollable),+> {
+            $(
+                #[allow(missing_docs)]
+                $variant(Result<(), $F::Error>)
+            ),+
+        }
+
+        #[must_use]
+        #[doc = concat!(
+            "Drives a tuple of differently-typed futures together, ",
+            "resolving as soon as the first one finishes. See the ",
+            "[module-level documentation](self) for how this relates to ",
+            "[`SelectAll`]."
+        )]
+        pub struct $name<$($F: Pollable),+> {
+            futures: ($($F,)+),
+            done: ($(bool,)+),
+        }
+
+        impl<$($F: Pollable),+> $name<$($F),+> {
+            #[doc = concat!("Create a new `", stringify!($name), "`, driving all of `futures` together")]
+            pub fn new(futures: ($($F,)+)) -> Self {
+                Self {
+                    futures,
+                    done: ($({ let _ = $idx; false },)+),
+                }
+            }
+
+            /// Poll every inner future that hasn't finished yet, in tuple
+            /// order, returning the result of the first one found to
+            /// have finished
+            pub fn poll(&mut self) -> Poll<$result<$($F),+>> {
+                $(
+                    if !self.done.$idx {
+                        if let Poll::Ready(result) = self.futures.$idx.poll() {
+                            self.done.$idx = true;
+                            return Poll::Ready($result::$variant(result));
+                        }
+                    }
+                )+
+
+                Poll::Pending
+            }
+
+            /// Wait until the first future finishes
+            ///
+            /// This method will call [`Self::poll`] in a busy loop until
+            /// one of the futures has finished.
+            pub fn wait(&mut self) -> $result<$($F),+> {
+                loop {
+                    if let Poll::Ready(result) = self.poll() {
+                        return result;
+                    }
+                }
+            }
+
+            /// Drop the futures and release the resources that were
+            /// moved into each of them
+            pub fn release(self) -> ($($F::Released,)+) {
+                ($(self.futures.$idx.release(),)+)
+            }
+        }
+    };
+}
+
+impl_heterogeneous_select!(Select2, Select2Result, (F0, 0, Axis0), (F1, 1, Axis1));
+impl_heterogeneous_select!(
+    Select3,
+    Select3Result,
+    (F0, 0, Axis0),
+    (F1, 1, Axis1),
+    (F2, 2, Axis2)
+);
+impl_heterogeneous_select!(
+    Select4,
+    Select4Result,
+    (F0, 0, Axis0),
+    (F1, 1, Axis1),
+    (F2, 2, Axis2),
+    (F3, 3, Axis3)
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A [`Pollable`] mock that becomes ready after a fixed number of
+    /// `poll` calls, yielding `outcome`, and panics if polled again after
+    /// that
+    #[derive(Debug, PartialEq)]
+    struct Mock {
+        polls_until_ready: u32,
+        outcome: Result<(), &'static str>,
+        finished: bool,
+    }
+
+    impl Mock {
+        fn new(polls_until_ready: u32, outcome: Result<(), &'static str>) -> Self {
+            Self {
+                polls_until_ready,
+                outcome,
+                finished: false,
+            }
+        }
+    }
+
+    impl Pollable for Mock {
+        type Error = &'static str;
+        type Released = ();
+
+        fn poll(&mut self) -> Poll<Result<(), Self::Error>> {
+            assert!(!self.finished, "polled again after it already finished");
+            if self.polls_until_ready == 0 {
+                self.finished = true;
+                Poll::Ready(self.outcome)
+            } else {
+                self.polls_until_ready -= 1;
+                Poll::Pending
+            }
+        }
+
+        fn release(self) -> Self::Released {}
+    }
+
+    #[test]
+    fn join_all_waits_for_the_slowest_future() {
+        let mut join = JoinAll::new([Mock::new(0, Ok(())), Mock::new(2, Ok(()))]);
+
+        assert_eq!(join.poll(), Poll::Pending);
+        assert_eq!(join.poll(), Poll::Pending);
+        assert_eq!(join.poll(), Poll::Ready([Ok(()), Ok(())]));
+    }
+
+    #[test]
+    fn join_all_does_not_re_poll_a_future_that_already_finished() {
+        // If index 0 were polled again after finishing, it would panic:
+        // `Mock::poll` asserts that it is never called again once it has
+        // returned `Poll::Ready`.
+        let mut join = JoinAll::new([Mock::new(0, Ok(())), Mock::new(1, Ok(()))]);
+
+        assert_eq!(join.poll(), Poll::Pending);
+        assert_eq!(join.poll(), Poll::Ready([Ok(()), Ok(())]));
+    }
+
+    #[test]
+    fn select_all_resolves_on_the_first_future_to_finish() {
+        let mut select = SelectAll::new([Mock::new(1, Ok(())), Mock::new(0, Ok(()))]);
+
+        assert_eq!(select.poll(), Poll::Ready((1, Ok(()))));
+    }
+
+    #[test]
+    fn select_all_reports_each_index_at_most_once() {
+        let mut select = SelectAll::new([Mock::new(0, Ok(())), Mock::new(1, Ok(()))]);
+
+        assert_eq!(select.poll(), Poll::Ready((0, Ok(()))));
+        // Index 0 already resolved; polling again must move on to index 1
+        // instead of reporting it a second time.
+        assert_eq!(select.poll(), Poll::Ready((1, Ok(()))));
+    }
+
+    /// A second [`Pollable`] mock with a different `Error`/`Released`
+    /// shape than [`Mock`], so tests can drive the heterogeneous `Join`/
+    /// `Select` combinators with genuinely differently-typed futures
+    #[derive(Debug, PartialEq)]
+    struct OtherAxisMock {
+        polls_until_ready: u32,
+        outcome: Result<(), u32>,
+    }
+
+    impl OtherAxisMock {
+        fn new(polls_until_ready: u32, outcome: Result<(), u32>) -> Self {
+            Self {
+                polls_until_ready,
+                outcome,
+            }
+        }
+    }
+
+    impl Pollable for OtherAxisMock {
+        type Error = u32;
+        type Released = &'static str;
+
+        fn poll(&mut self) -> Poll<Result<(), Self::Error>> {
+            if self.polls_until_ready == 0 {
+                Poll::Ready(self.outcome)
+            } else {
+                self.polls_until_ready -= 1;
+                Poll::Pending
+            }
+        }
+
+        fn release(self) -> Self::Released {
+            "other-axis"
+        }
+    }
+
+    #[test]
+    fn join2_waits_for_both_differently_typed_futures() {
+        let mut join = Join2::new((Mock::new(0, Ok(())), OtherAxisMock::new(2, Ok(()))));
+
+        assert_eq!(join.poll(), Poll::Pending);
+        assert_eq!(join.poll(), Poll::Pending);
+        assert_eq!(join.poll(), Poll::Ready((Ok(()), Ok(()))));
+    }
+
+    #[test]
+    fn join2_does_not_re_poll_a_future_that_already_finished() {
+        // If the first slot were polled again after finishing, it would
+        // panic: `Mock::poll` asserts that it is never called again once it
+        // has returned `Poll::Ready`.
+        let mut join = Join2::new((Mock::new(0, Ok(())), OtherAxisMock::new(1, Ok(()))));
+
+        assert_eq!(join.poll(), Poll::Pending);
+        assert_eq!(join.poll(), Poll::Ready((Ok(()), Ok(()))));
+    }
+
+    #[test]
+    fn select2_resolves_on_the_first_future_to_finish() {
+        let mut select = Select2::new((Mock::new(1, Ok(())), OtherAxisMock::new(0, Ok(()))));
+
+        assert_eq!(select.poll(), Poll::Ready(Select2Result::Axis1(Ok(()))));
+    }
+
+    #[test]
+    fn select2_reports_each_axis_at_most_once() {
+        let mut select = Select2::new((Mock::new(0, Ok(())), OtherAxisMock::new(1, Ok(()))));
+
+        assert_eq!(select.poll(), Poll::Ready(Select2Result::Axis0(Ok(()))));
+        // The first axis already resolved; polling again must move on to
+        // the second axis instead of reporting it a second time.
+        assert_eq!(select.poll(), Poll::Ready(Select2Result::Axis1(Ok(()))));
+    }
+}
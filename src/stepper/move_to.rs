@@ -2,6 +2,9 @@ use core::task::Poll;
 
 use crate::traits::MotionControl;
 
+#[cfg(feature = "async")]
+use super::waker::SharedState;
+
 /// The "future" returned by [`Stepper::move_to_position`]
 ///
 /// Please note that this type provides a custom API and does not implement
@@ -13,6 +16,8 @@ use crate::traits::MotionControl;
 pub struct MoveToFuture<Driver: MotionControl> {
     driver: Driver,
     state: State<Driver::Velocity>,
+    #[cfg(feature = "async")]
+    shared_state: SharedState,
 }
 
 impl<Driver> MoveToFuture<Driver>
@@ -37,6 +42,8 @@ where
                 max_velocity,
                 target_step,
             },
+            #[cfg(feature = "async")]
+            shared_state: SharedState::new(),
         }
     }
 
@@ -116,24 +123,33 @@ where
         self: core::pin::Pin<&mut Self>,
         cx: &mut core::task::Context<'_>,
     ) -> Poll<Self::Output> {
-        // match Self::poll(self.get_mut()) {
-        //     Poll::Ready(output) => Poll::Ready(output),
-        //     Poll::Pending => {
-        //         let fut = embassy_time::Timer::after_millis(2);
-        //         let mut pinned_fut = core::pin::pin!(fut);
+        let this = self.get_mut();
 
-        //         match pinned_fut.as_mut().poll(cx) {
-        //             Poll::Pending => Poll::Pending,
-        //             Poll::Ready(()) => panic!("Should not be ready"),
-        //         }
-        //     }
-        // }
-
-        if let Poll::Ready(output) = Self::poll(self.get_mut()) {
-            Poll::Ready(output)
-        } else {
-            cx.waker().wake_by_ref();
-            Poll::Pending
+        match Self::poll(this) {
+            Poll::Ready(output) => {
+                this.shared_state.wake();
+                Poll::Ready(output)
+            }
+            Poll::Pending => {
+                this.shared_state.register(cx.waker());
+                Poll::Pending
+            }
         }
     }
 }
+
+#[cfg(feature = "async")]
+impl<Driver> MoveToFuture<Driver>
+where
+    Driver: MotionControl,
+{
+    /// Notify this future that the motion controller's next step interval
+    /// has elapsed
+    ///
+    /// See the [`waker`](super::waker) module for the synchronization
+    /// contract that makes it safe to call this from a timer interrupt
+    /// handler that preempts `poll`.
+    pub fn on_timer_interrupt(&self) {
+        self.shared_state.wake();
+    }
+}
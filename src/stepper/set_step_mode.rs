@@ -10,6 +10,9 @@ use crate::traits::SetStepMode;
 
 use super::{Error, Stepper};
 
+#[cfg(feature = "async")]
+use super::waker::SharedState;
+
 /// A "future" that can be polled to complete a [`Stepper::set_step_mode`] call
 ///
 /// Please note that this type provides a custom API and does not implement
@@ -20,6 +23,8 @@ pub struct SetStepModeFuture<'r, Driver: SetStepMode, Timer> {
     stepper: &'r mut Stepper<Driver>,
     timer: &'r mut Timer,
     state: State,
+    #[cfg(feature = "async")]
+    shared_state: SharedState,
 }
 
 impl<'r, Driver, Timer> SetStepModeFuture<'r, Driver, Timer>
@@ -38,6 +43,8 @@ where
             stepper,
             timer,
             state: State::Initial,
+            #[cfg(feature = "async")]
+            shared_state: SharedState::new(),
         }
     }
 
@@ -148,3 +155,58 @@ enum State {
     EnablingDriver,
     Finished,
 }
+
+#[cfg(feature = "async")]
+use core::future::Future;
+
+#[cfg(feature = "async")]
+impl<'r, Driver, Timer> Future for SetStepModeFuture<'r, Driver, Timer>
+where
+    Driver: SetStepMode,
+    Timer: timer::CountDown,
+    Timer::Time: TryFrom<Nanoseconds>,
+{
+    type Output = Result<
+        (),
+        Error<
+            Driver::Error,
+            <Timer::Time as TryFrom<Nanoseconds>>::Error,
+            Timer::Error,
+        >,
+    >;
+
+    fn poll(
+        self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        match Self::poll(this) {
+            Poll::Ready(output) => {
+                this.shared_state.wake();
+                Poll::Ready(output)
+            }
+            Poll::Pending => {
+                this.shared_state.register(cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<'r, Driver, Timer> SetStepModeFuture<'r, Driver, Timer>
+where
+    Driver: SetStepMode,
+    Timer: timer::CountDown,
+    Timer::Time: TryFrom<Nanoseconds>,
+{
+    /// Notify this future that the setup or hold time has elapsed
+    ///
+    /// See the [`waker`](super::waker) module for the synchronization
+    /// contract that makes it safe to call this from a timer interrupt
+    /// handler that preempts `poll`.
+    pub fn on_timer_interrupt(&self) {
+        self.shared_state.wake();
+    }
+}
@@ -9,6 +9,9 @@ use crate::traits::Step;
 
 use super::SignalError;
 
+#[cfg(feature = "async")]
+use super::waker::SharedState;
+
 /// The "future" returned by [`Stepper::step`]
 ///
 /// Please note that this type provides a custom API and does not implement
@@ -21,6 +24,8 @@ pub struct StepFuture<Driver, Timer, const TIMER_HZ: u32> {
     driver: Driver,
     timer: Timer,
     state: State,
+    #[cfg(feature = "async")]
+    shared_state: SharedState,
 }
 
 impl<Driver, Timer, const TIMER_HZ: u32> StepFuture<Driver, Timer, TIMER_HZ>
@@ -40,6 +45,8 @@ where
             driver,
             timer,
             state: State::Initial,
+            #[cfg(feature = "async")]
+            shared_state: SharedState::new(),
         }
     }
 
@@ -163,24 +170,33 @@ where
         self: core::pin::Pin<&mut Self>,
         cx: &mut core::task::Context<'_>,
     ) -> Poll<Self::Output> {
-        // match Self::poll(self.get_mut()) {
-        //     Poll::Ready(output) => Poll::Ready(output),
-        //     Poll::Pending => {
-        //         let fut = embassy_time::Timer::after_millis(2);
-        //         let mut pinned_fut = core::pin::pin!(fut);
-
-        //         match pinned_fut.as_mut().poll(cx) {
-        //             Poll::Pending => Poll::Pending,
-        //             Poll::Ready(()) => panic!("Should not be ready"),
-        //         }
-        //     }
-        // }
-
-        if let Poll::Ready(output) = Self::poll(self.get_mut()) {
-            Poll::Ready(output)
-        } else {
-            cx.waker().wake_by_ref();
-            Poll::Pending
+        let this = self.get_mut();
+
+        match Self::poll(this) {
+            Poll::Ready(output) => {
+                this.shared_state.wake();
+                Poll::Ready(output)
+            }
+            Poll::Pending => {
+                this.shared_state.register(cx.waker());
+                Poll::Pending
+            }
         }
     }
 }
+
+#[cfg(feature = "async")]
+impl<Driver, Timer, const TIMER_HZ: u32> StepFuture<Driver, Timer, TIMER_HZ>
+where
+    Driver: Step,
+    Timer: TimerTrait<TIMER_HZ>,
+{
+    /// Notify this future that the step pulse's length has elapsed
+    ///
+    /// See the [`waker`](super::waker) module for the synchronization
+    /// contract that makes it safe to call this from a timer interrupt
+    /// handler that preempts `poll`.
+    pub fn on_timer_interrupt(&self) {
+        self.shared_state.wake();
+    }
+}
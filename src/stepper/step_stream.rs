@@ -0,0 +1,322 @@
+//! A continuous, drift-free source of steps for open-loop velocity control
+//!
+//! See [`StepStream`].
+
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use embedded_hal::digital::{ErrorType, OutputPin};
+use fugit::{HertzU32, TimerDurationU32 as TimerDuration, TimerInstantU32 as TimerInstant};
+use fugit_timer::Timer as TimerTrait;
+use futures_core::Stream;
+use futures_util::stream::FusedStream;
+
+use crate::traits::Step;
+
+use super::waker::SharedState;
+use super::SignalError;
+
+/// A continuous source of steps, driven at a configurable frequency
+///
+/// Unlike [`StepFuture`], which resolves once a single step pulse has
+/// completed, `StepStream` keeps emitting steps at its configured
+/// frequency until it is [stopped](Self::stop) or its step budget (see
+/// [`Self::with_step_budget`]) is exhausted. It implements [`Stream`] and
+/// [`FusedStream`], so it can be driven from a `select!` loop alongside
+/// other events, which is what open-loop velocity control and jogging
+/// need.
+///
+/// Each tick is re-armed from the *expected* instant of the previous tick,
+/// rather than from `now`, so that scheduling jitter doesn't accumulate
+/// into long-term drift.
+///
+/// [`StepFuture`]: super::step::StepFuture
+#[must_use]
+pub struct StepStream<Driver, Timer, const TIMER_HZ: u32>
+where
+    Driver: Step,
+    Timer: TimerTrait<TIMER_HZ>,
+{
+    driver: Driver,
+    timer: Timer,
+    period: TimerDuration<TIMER_HZ>,
+    next_tick: TimerInstant<TIMER_HZ>,
+    state: State,
+    steps_remaining: Option<u32>,
+    stopped: bool,
+    shared_state: SharedState,
+}
+
+impl<Driver, Timer, const TIMER_HZ: u32> StepStream<Driver, Timer, TIMER_HZ>
+where
+    Driver: Step,
+    Timer: TimerTrait<TIMER_HZ>,
+{
+    /// Create a new `StepStream`, stepping at `frequency`
+    ///
+    /// The stream has no step budget by default and keeps running until
+    /// [`Self::stop`] is called. Use [`Self::with_step_budget`] to make it
+    /// stop on its own after a fixed number of steps.
+    pub fn new(driver: Driver, timer: Timer, frequency: HertzU32) -> Self {
+        Self {
+            driver,
+            timer,
+            period: clamp_period(frequency.into_duration(), Driver::PULSE_LENGTH.convert()),
+            next_tick: TimerInstant::from_ticks(0),
+            state: State::Idle,
+            steps_remaining: None,
+            stopped: false,
+            shared_state: SharedState::new(),
+        }
+    }
+
+    /// Limit this stream to `steps` steps
+    ///
+    /// Once that many steps have been emitted, [`FusedStream::is_terminated`]
+    /// reports `true` and further polling yields `None`. A budget of `0`
+    /// terminates the stream immediately, without ever starting a pulse.
+    pub fn with_step_budget(mut self, steps: u32) -> Self {
+        self.steps_remaining = Some(steps);
+        if steps == 0 {
+            self.stopped = true;
+        }
+        self
+    }
+
+    /// Change the step frequency
+    ///
+    /// Takes effect from the next tick onward; the interval already in
+    /// progress is not affected. This is what lets a caller speed up or
+    /// slow down a jog without tearing the stream down and rebuilding it.
+    ///
+    /// `frequency` is clamped so that the resulting period is never
+    /// shorter than [`Driver::PULSE_LENGTH`](Step::PULSE_LENGTH); a period
+    /// that short would mean the next tick is already due (or overdue) by
+    /// the time the current pulse ends.
+    pub fn set_frequency(&mut self, frequency: HertzU32) {
+        self.period = clamp_period(frequency.into_duration(), Driver::PULSE_LENGTH.convert());
+    }
+
+    /// Stop the stream
+    ///
+    /// After this call, [`FusedStream::is_terminated`] reports `true` and
+    /// further polling yields `None`.
+    pub fn stop(&mut self) {
+        self.stopped = true;
+    }
+
+    /// Notify this stream that the current pulse or interval has elapsed
+    ///
+    /// See the [`waker`](super::waker) module for the synchronization
+    /// contract that makes it safe to call this from a timer interrupt
+    /// handler that preempts `poll_next`.
+    pub fn on_timer_interrupt(&self) {
+        self.shared_state.wake();
+    }
+
+    /// Drop the stream and release the resources that were moved into it
+    pub fn release(self) -> (Driver, Timer) {
+        (self.driver, self.timer)
+    }
+
+    fn start_pulse(
+        &mut self,
+    ) -> Result<(), SignalError<Driver::Error, <Driver::Step as ErrorType>::Error, Timer::Error>>
+    {
+        self.driver
+            .step()
+            .map_err(SignalError::PinUnavailable)?
+            .set_high()
+            .map_err(SignalError::Pin)?;
+
+        let ticks: TimerDuration<TIMER_HZ> = Driver::PULSE_LENGTH.convert();
+        self.timer.start(ticks).map_err(SignalError::Timer)?;
+
+        Ok(())
+    }
+
+    fn arm_next_tick(
+        &mut self,
+    ) -> Result<(), SignalError<Driver::Error, <Driver::Step as ErrorType>::Error, Timer::Error>>
+    {
+        self.driver
+            .step()
+            .map_err(SignalError::PinUnavailable)?
+            .set_low()
+            .map_err(SignalError::Pin)?;
+
+        self.next_tick = self.next_tick + self.period;
+
+        let now = self.timer.now();
+        self.timer
+            .start(saturating_duration_until(self.next_tick, now))
+            .map_err(SignalError::Timer)?;
+
+        Ok(())
+    }
+}
+
+enum State {
+    Idle,
+    PulseStarted,
+    WaitingForNextTick,
+}
+
+/// Clamp `period` so it's never shorter than `min_period`
+///
+/// Split out from [`StepStream::set_frequency`] so it can be unit tested
+/// without a full `Driver`/`Timer` pair.
+fn clamp_period<const TIMER_HZ: u32>(
+    period: TimerDuration<TIMER_HZ>,
+    min_period: TimerDuration<TIMER_HZ>,
+) -> TimerDuration<TIMER_HZ> {
+    if period < min_period {
+        min_period
+    } else {
+        period
+    }
+}
+
+/// The duration between `now` and `next_tick`, or zero if `next_tick` is
+/// already in the past
+///
+/// Split out from [`StepStream::arm_next_tick`] so it can be unit tested
+/// without a full `Driver`/`Timer` pair.
+fn saturating_duration_until<const TIMER_HZ: u32>(
+    next_tick: TimerInstant<TIMER_HZ>,
+    now: TimerInstant<TIMER_HZ>,
+) -> TimerDuration<TIMER_HZ> {
+    if next_tick > now {
+        next_tick - now
+    } else {
+        TimerDuration::from_ticks(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saturating_duration_until_returns_the_gap_when_the_tick_is_still_ahead() {
+        let now = TimerInstant::<1_000_000>::from_ticks(1_000);
+        let next_tick = TimerInstant::<1_000_000>::from_ticks(1_500);
+
+        assert_eq!(
+            saturating_duration_until(next_tick, now),
+            TimerDuration::<1_000_000>::from_ticks(500)
+        );
+    }
+
+    #[test]
+    fn saturating_duration_until_clamps_to_zero_instead_of_underflowing() {
+        let now = TimerInstant::<1_000_000>::from_ticks(1_500);
+        let next_tick = TimerInstant::<1_000_000>::from_ticks(1_000);
+
+        assert_eq!(
+            saturating_duration_until(next_tick, now),
+            TimerDuration::<1_000_000>::from_ticks(0)
+        );
+    }
+
+    #[test]
+    fn clamp_period_leaves_a_long_enough_period_alone() {
+        let period = TimerDuration::<1_000_000>::from_ticks(2_000);
+        let min_period = TimerDuration::<1_000_000>::from_ticks(500);
+
+        assert_eq!(clamp_period(period, min_period), period);
+    }
+
+    #[test]
+    fn clamp_period_raises_a_too_short_period_to_the_pulse_length() {
+        let period = TimerDuration::<1_000_000>::from_ticks(100);
+        let min_period = TimerDuration::<1_000_000>::from_ticks(500);
+
+        assert_eq!(clamp_period(period, min_period), min_period);
+    }
+}
+
+impl<Driver, Timer, const TIMER_HZ: u32> Stream for StepStream<Driver, Timer, TIMER_HZ>
+where
+    Driver: Step + Unpin,
+    Timer: TimerTrait<TIMER_HZ> + Unpin,
+{
+    type Item =
+        Result<(), SignalError<Driver::Error, <Driver::Step as ErrorType>::Error, Timer::Error>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.is_terminated() {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            match this.state {
+                State::Idle => {
+                    this.next_tick = this.timer.now();
+
+                    if let Err(err) = this.start_pulse() {
+                        this.stopped = true;
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    this.state = State::PulseStarted;
+                }
+                State::PulseStarted => match this.timer.wait() {
+                    Ok(()) => {
+                        if let Err(err) = this.arm_next_tick() {
+                            this.stopped = true;
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                        this.state = State::WaitingForNextTick;
+                    }
+                    Err(nb::Error::Other(err)) => {
+                        this.stopped = true;
+                        return Poll::Ready(Some(Err(SignalError::Timer(err))));
+                    }
+                    Err(nb::Error::WouldBlock) => {
+                        this.shared_state.register(cx.waker());
+                        return Poll::Pending;
+                    }
+                },
+                State::WaitingForNextTick => match this.timer.wait() {
+                    Ok(()) => {
+                        if let Some(steps_remaining) = &mut this.steps_remaining {
+                            *steps_remaining = steps_remaining.saturating_sub(1);
+                            if *steps_remaining == 0 {
+                                this.stopped = true;
+                                return Poll::Ready(Some(Ok(())));
+                            }
+                        }
+
+                        if let Err(err) = this.start_pulse() {
+                            this.stopped = true;
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                        this.state = State::PulseStarted;
+
+                        return Poll::Ready(Some(Ok(())));
+                    }
+                    Err(nb::Error::Other(err)) => {
+                        this.stopped = true;
+                        return Poll::Ready(Some(Err(SignalError::Timer(err))));
+                    }
+                    Err(nb::Error::WouldBlock) => {
+                        this.shared_state.register(cx.waker());
+                        return Poll::Pending;
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<Driver, Timer, const TIMER_HZ: u32> FusedStream for StepStream<Driver, Timer, TIMER_HZ>
+where
+    Driver: Step + Unpin,
+    Timer: TimerTrait<TIMER_HZ> + Unpin,
+{
+    fn is_terminated(&self) -> bool {
+        self.stopped
+    }
+}
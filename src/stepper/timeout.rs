@@ -0,0 +1,387 @@
+//! A combinator that aborts a motion or step if it overruns a deadline
+//!
+//! See [`TimeoutExt`].
+
+use core::task::Poll;
+
+use embedded_hal::digital::ErrorType;
+use fugit::TimerDurationU32 as TimerDuration;
+use fugit_timer::Timer as TimerTrait;
+
+use crate::traits::{MotionControl, Step};
+
+use super::move_to::MoveToFuture;
+use super::step::StepFuture;
+
+#[cfg(feature = "async")]
+use super::waker::SharedState;
+
+/// A type that can be polled and released in the manual "future" style used
+/// throughout this crate
+///
+/// This is what [`Timeout`] wraps. It is implemented for [`StepFuture`] and
+/// [`MoveToFuture`].
+pub trait Pollable {
+    /// The error returned once polling is finished and has failed
+    type Error;
+
+    /// The resources released once polling is finished
+    type Released;
+
+    /// Poll the operation, same as the inherent `poll` method of the type
+    /// that implements this trait
+    fn poll(&mut self) -> Poll<Result<(), Self::Error>>;
+
+    /// Release the resources that were moved into the operation
+    fn release(self) -> Self::Released;
+}
+
+impl<Driver, Timer, const TIMER_HZ: u32> Pollable for StepFuture<Driver, Timer, TIMER_HZ>
+where
+    Driver: Step,
+    Timer: TimerTrait<TIMER_HZ>,
+{
+    type Error =
+        super::SignalError<Driver::Error, <Driver::Step as ErrorType>::Error, Timer::Error>;
+    type Released = (Driver, Timer);
+
+    fn poll(&mut self) -> Poll<Result<(), Self::Error>> {
+        StepFuture::poll(self)
+    }
+
+    fn release(self) -> Self::Released {
+        StepFuture::release(self)
+    }
+}
+
+impl<Driver> Pollable for MoveToFuture<Driver>
+where
+    Driver: MotionControl,
+{
+    type Error = Driver::Error;
+    type Released = Driver;
+
+    fn poll(&mut self) -> Poll<Result<(), Self::Error>> {
+        MoveToFuture::poll(self)
+    }
+
+    fn release(self) -> Self::Released {
+        MoveToFuture::release(self)
+    }
+}
+
+/// An error that occurred while polling a [`Timeout`]
+#[derive(Debug, PartialEq)]
+pub enum TimeoutError<Err, TimerErr> {
+    /// The wrapped operation failed before the deadline was reached
+    Inner(Err),
+
+    /// The timer that tracks the deadline failed
+    Timer(TimerErr),
+
+    /// The deadline was reached before the wrapped operation finished
+    TimedOut,
+}
+
+/// Races a [`Pollable`] operation against a deadline
+///
+/// Returned by [`TimeoutExt::with_timeout`]. On every `poll`, the wrapped
+/// operation is polled first; only if it is still pending does the
+/// deadline timer get a chance to fire. If the deadline is reached first,
+/// `poll` resolves to [`TimeoutError::TimedOut`] and the wrapped operation
+/// is left exactly as it was, so its resources can still be recovered
+/// through [`Self::release`].
+#[must_use]
+pub struct Timeout<F, Timer, const TIMER_HZ: u32> {
+    inner: F,
+    timer: Timer,
+    state: State,
+    #[cfg(feature = "async")]
+    shared_state: SharedState,
+}
+
+impl<F, Timer, const TIMER_HZ: u32> Timeout<F, Timer, TIMER_HZ>
+where
+    F: Pollable,
+    Timer: TimerTrait<TIMER_HZ>,
+{
+    fn new(
+        inner: F,
+        mut timer: Timer,
+        deadline: TimerDuration<TIMER_HZ>,
+    ) -> Result<Self, Timer::Error> {
+        timer.start(deadline)?;
+
+        Ok(Self {
+            inner,
+            timer,
+            state: State::Running,
+            #[cfg(feature = "async")]
+            shared_state: SharedState::new(),
+        })
+    }
+
+    /// Poll the operation
+    ///
+    /// Returns [`Poll::Pending`], if neither the wrapped operation nor the
+    /// deadline has resolved yet, or [`Poll::Ready`], once either has.
+    pub fn poll(&mut self) -> Poll<Result<(), TimeoutError<F::Error, Timer::Error>>> {
+        match self.state {
+            State::Running => {
+                if let Poll::Ready(result) = self.inner.poll() {
+                    self.state = State::Finished;
+                    return Poll::Ready(result.map_err(TimeoutError::Inner));
+                }
+
+                match self.timer.wait() {
+                    Ok(()) => {
+                        self.state = State::Finished;
+                        Poll::Ready(Err(TimeoutError::TimedOut))
+                    }
+                    Err(nb::Error::Other(err)) => {
+                        self.state = State::Finished;
+                        Poll::Ready(Err(TimeoutError::Timer(err)))
+                    }
+                    Err(nb::Error::WouldBlock) => Poll::Pending,
+                }
+            }
+            State::Finished => Poll::Ready(Ok(())),
+        }
+    }
+
+    /// Wait until the operation completes or the deadline is reached
+    ///
+    /// This method will call [`Self::poll`] in a busy loop until the
+    /// operation has finished.
+    pub fn wait(&mut self) -> Result<(), TimeoutError<F::Error, Timer::Error>> {
+        loop {
+            if let Poll::Ready(result) = self.poll() {
+                return result;
+            }
+        }
+    }
+
+    /// Drop the timeout and release the resources moved into the wrapped
+    /// operation
+    ///
+    /// This works whether the deadline was reached or not, which is what
+    /// makes a timed-out driver recoverable rather than stuck.
+    pub fn release(self) -> F::Released {
+        self.inner.release()
+    }
+}
+
+enum State {
+    Running,
+    Finished,
+}
+
+#[cfg(feature = "async")]
+use core::future::Future;
+
+// This impl requires `F: Future` too, on top of `Pollable`, even though the
+// manual `Self::poll` above only needs `Pollable`. Driving the inner
+// operation through `Pollable::poll` (its bare, non-async `poll`) would
+// never register `cx.waker()` with the inner operation's own
+// `SharedState`, so an interrupt that completes the wrapped `StepFuture`/
+// `MoveToFuture` before the deadline would have no way to wake a real
+// executor; it would only get re-polled once the deadline timer itself
+// fires. Polling `F` as a `Future` instead threads `cx` all the way down,
+// so the inner operation's own waker-based completion path (see
+// [`waker`](super::waker)) is what wakes this future, same as it would if
+// `F` were awaited directly.
+#[cfg(feature = "async")]
+impl<F, Timer, const TIMER_HZ: u32> Future for Timeout<F, Timer, TIMER_HZ>
+where
+    F: Pollable + Future<Output = Result<(), <F as Pollable>::Error>> + Unpin,
+    Timer: TimerTrait<TIMER_HZ> + Unpin,
+{
+    type Output = Result<(), TimeoutError<F::Error, Timer::Error>>;
+
+    fn poll(
+        self: core::pin::Pin<&mut Self>,
+        cx: &mut core::task::Context<'_>,
+    ) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        match this.state {
+            State::Running => {
+                if let Poll::Ready(result) = core::pin::Pin::new(&mut this.inner).poll(cx) {
+                    this.state = State::Finished;
+                    this.shared_state.wake();
+                    return Poll::Ready(result.map_err(TimeoutError::Inner));
+                }
+
+                match this.timer.wait() {
+                    Ok(()) => {
+                        this.state = State::Finished;
+                        this.shared_state.wake();
+                        Poll::Ready(Err(TimeoutError::TimedOut))
+                    }
+                    Err(nb::Error::Other(err)) => {
+                        this.state = State::Finished;
+                        this.shared_state.wake();
+                        Poll::Ready(Err(TimeoutError::Timer(err)))
+                    }
+                    Err(nb::Error::WouldBlock) => {
+                        this.shared_state.register(cx.waker());
+                        Poll::Pending
+                    }
+                }
+            }
+            State::Finished => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F, Timer, const TIMER_HZ: u32> Timeout<F, Timer, TIMER_HZ>
+where
+    F: Pollable,
+    Timer: TimerTrait<TIMER_HZ>,
+{
+    /// Notify this future that the deadline timer has fired
+    ///
+    /// See the [`waker`](super::waker) module for the synchronization
+    /// contract that makes it safe to call this from a timer interrupt
+    /// handler that preempts `poll`.
+    pub fn on_timer_interrupt(&self) {
+        self.shared_state.wake();
+    }
+}
+
+/// Adds [`Self::with_timeout`] to the futures defined in this crate
+pub trait TimeoutExt: Pollable + Sized {
+    /// Wrap this operation in a [`Timeout`]
+    ///
+    /// `timer` is started for `deadline` right away. If the wrapped
+    /// operation has not resolved by the time `timer` fires, the returned
+    /// [`Timeout`] resolves to [`TimeoutError::TimedOut`] instead of
+    /// letting the operation run forever.
+    fn with_timeout<Timer, const TIMER_HZ: u32>(
+        self,
+        timer: Timer,
+        deadline: TimerDuration<TIMER_HZ>,
+    ) -> Result<Timeout<Self, Timer, TIMER_HZ>, Timer::Error>
+    where
+        Timer: TimerTrait<TIMER_HZ>,
+    {
+        Timeout::new(self, timer, deadline)
+    }
+}
+
+impl<F> TimeoutExt for F where F: Pollable {}
+
+#[cfg(test)]
+mod tests {
+    use fugit::TimerInstantU32;
+
+    use super::*;
+
+    /// A [`Pollable`] mock that becomes ready after a fixed number of
+    /// `poll` calls, yielding `outcome`
+    struct MockOperation {
+        polls_until_ready: u32,
+        outcome: Result<(), &'static str>,
+    }
+
+    impl MockOperation {
+        fn new(polls_until_ready: u32, outcome: Result<(), &'static str>) -> Self {
+            Self {
+                polls_until_ready,
+                outcome,
+            }
+        }
+    }
+
+    impl Pollable for MockOperation {
+        type Error = &'static str;
+        type Released = ();
+
+        fn poll(&mut self) -> Poll<Result<(), Self::Error>> {
+            if self.polls_until_ready == 0 {
+                Poll::Ready(self.outcome)
+            } else {
+                self.polls_until_ready -= 1;
+                Poll::Pending
+            }
+        }
+
+        fn release(self) -> Self::Released {}
+    }
+
+    /// A [`TimerTrait`] mock whose deadline fires after a fixed number of
+    /// `wait` calls
+    struct MockTimer {
+        waits_until_fired: u32,
+    }
+
+    impl TimerTrait<1_000_000> for MockTimer {
+        type Error = ();
+
+        fn now(&mut self) -> TimerInstantU32<1_000_000> {
+            TimerInstantU32::from_ticks(0)
+        }
+
+        fn start(&mut self, _duration: TimerDuration<1_000_000>) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn cancel(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn wait(&mut self) -> nb::Result<(), Self::Error> {
+            if self.waits_until_fired == 0 {
+                Ok(())
+            } else {
+                self.waits_until_fired -= 1;
+                Err(nb::Error::WouldBlock)
+            }
+        }
+    }
+
+    fn timeout(
+        inner: MockOperation,
+        waits_until_fired: u32,
+    ) -> Timeout<MockOperation, MockTimer, 1_000_000> {
+        Timeout::new(
+            inner,
+            MockTimer { waits_until_fired },
+            TimerDuration::from_ticks(1_000),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn inner_resolving_before_the_deadline_never_times_out() {
+        let mut timeout = timeout(MockOperation::new(1, Ok(())), 10);
+
+        assert_eq!(timeout.wait(), Ok(()));
+    }
+
+    #[test]
+    fn inner_and_deadline_ready_on_the_same_poll_favors_the_inner_result() {
+        // The inner operation is checked before the deadline timer, so it
+        // wins even though both are ready on this poll.
+        let mut timeout = timeout(MockOperation::new(0, Ok(())), 0);
+
+        assert_eq!(timeout.poll(), Poll::Ready(Ok(())));
+    }
+
+    #[test]
+    fn deadline_firing_first_times_out() {
+        let mut timeout = timeout(MockOperation::new(10, Ok(())), 1);
+
+        assert_eq!(timeout.poll(), Poll::Pending);
+        assert_eq!(timeout.poll(), Poll::Ready(Err(TimeoutError::TimedOut)));
+    }
+
+    #[test]
+    fn release_after_a_timeout_still_recovers_the_inner_resources() {
+        let mut timeout = timeout(MockOperation::new(10, Ok(())), 0);
+
+        assert_eq!(timeout.poll(), Poll::Ready(Err(TimeoutError::TimedOut)));
+        assert_eq!(timeout.release(), ());
+    }
+}
@@ -0,0 +1,180 @@
+//! Shared state used to bridge a polled future with its [`Waker`]
+//!
+//! This backs the [`core::future::Future`] implementations of
+//! [`StepFuture`], [`MoveToFuture`], and [`SetStepModeFuture`], so that
+//! pending futures can be woken once by whatever drives the underlying
+//! timer to completion, instead of re-waking themselves on every poll.
+//!
+//! # Synchronization contract
+//!
+//! [`SharedState::wake`] is safe to call concurrently with
+//! [`SharedState::register`] —
+//! for example from a hardware timer interrupt handler that can preempt
+//! `poll` at any point. The waker slot is protected by a
+//! [`critical_section`], and completion is tracked with an
+//! [`AtomicBool`], so a `wake` that lands between the inner operation
+//! reporting [`Poll::Pending`][core::task::Poll::Pending] and the waker
+//! being stored is never lost: `register` checks for a completion that
+//! already happened, both before and after it stores the waker, and wakes
+//! immediately if it finds one. This is why both methods take `&self`
+//! rather than `&mut self` — the whole point is that a caller with only
+//! shared access (the interrupt handler) can still safely signal
+//! completion.
+//!
+//! `register` consumes the completion flag as soon as it observes it (it's
+//! a swap, not a load), rather than leaving it latched `true` forever.
+//! That's what lets a single `SharedState` be reused across many
+//! wait/wake cycles, such as [`StepStream`] re-arming its timer after
+//! every tick — each `wake` only satisfies the `register` call it's
+//! paired with, instead of making every later `register` call return
+//! immediately regardless of whether the timer has actually fired again.
+//!
+//! [`StepFuture`]: super::step::StepFuture
+//! [`MoveToFuture`]: super::move_to::MoveToFuture
+//! [`SetStepModeFuture`]: super::set_step_mode::SetStepModeFuture
+//! [`StepStream`]: super::step_stream::StepStream
+
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Waker;
+
+use critical_section::Mutex;
+
+/// Tracks whether an operation has completed and, if not, the [`Waker`] to
+/// notify once it does
+///
+/// See the [module-level documentation](self) for the synchronization
+/// contract between [`Self::register`] and [`Self::wake`].
+pub(crate) struct SharedState {
+    completed: AtomicBool,
+    waker: Mutex<RefCell<Option<Waker>>>,
+}
+
+impl SharedState {
+    pub(crate) const fn new() -> Self {
+        Self {
+            completed: AtomicBool::new(false),
+            waker: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    /// Store the waker for the task that is currently polling
+    ///
+    /// Replaces any previously registered waker. Called from `poll`, right
+    /// before returning [`Poll::Pending`][core::task::Poll::Pending].
+    ///
+    /// If [`Self::wake`] has already run (or runs while this call is
+    /// storing the waker), this wakes `waker` immediately instead of
+    /// parking it, so a completion that races with `register` can't be
+    /// missed. Either way, the completion flag is consumed, so the next
+    /// `register` call starts fresh and waits for a new `wake`.
+    pub(crate) fn register(&self, waker: &Waker) {
+        if self.completed.swap(false, Ordering::AcqRel) {
+            waker.wake_by_ref();
+            return;
+        }
+
+        critical_section::with(|cs| {
+            *self.waker.borrow(cs).borrow_mut() = Some(waker.clone());
+        });
+
+        if self.completed.swap(false, Ordering::AcqRel) {
+            if let Some(waker) = self.take_waker() {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Wake the registered task, or, if none is registered yet, mark the
+    /// operation as completed so that the next [`Self::register`] call
+    /// wakes immediately
+    ///
+    /// Call this once, when the timer backing the operation fires, whether
+    /// that's from a hardware timer interrupt handler or the completion of
+    /// an `embassy-time`/`embedded-hal-async` delay.
+    pub(crate) fn wake(&self) {
+        let waker = critical_section::with(|cs| {
+            let waker = self.waker.borrow(cs).borrow_mut().take();
+            if waker.is_none() {
+                self.completed.store(true, Ordering::Release);
+            }
+            waker
+        });
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn take_waker(&self) -> Option<Waker> {
+        critical_section::with(|cs| self.waker.borrow(cs).borrow_mut().take())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::task::{RawWaker, RawWakerVTable};
+
+    use super::*;
+
+    /// Build a [`Waker`] that records whether it was woken into `flag`
+    fn flag_waker(flag: &AtomicBool) -> Waker {
+        unsafe fn clone(ptr: *const ()) -> RawWaker {
+            RawWaker::new(ptr, &VTABLE)
+        }
+
+        unsafe fn wake(ptr: *const ()) {
+            wake_by_ref(ptr);
+        }
+
+        unsafe fn wake_by_ref(ptr: *const ()) {
+            (*ptr.cast::<AtomicBool>()).store(true, Ordering::SeqCst);
+        }
+
+        unsafe fn drop(_: *const ()) {}
+
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop);
+
+        let raw = RawWaker::new((flag as *const AtomicBool).cast(), &VTABLE);
+        unsafe { Waker::from_raw(raw) }
+    }
+
+    #[test]
+    fn wake_before_register_wakes_immediately() {
+        let state = SharedState::new();
+        state.wake();
+
+        let woken = AtomicBool::new(false);
+        state.register(&flag_waker(&woken));
+
+        assert!(woken.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn register_then_wake_wakes_once() {
+        let state = SharedState::new();
+
+        let woken = AtomicBool::new(false);
+        state.register(&flag_waker(&woken));
+        assert!(!woken.load(Ordering::SeqCst));
+
+        state.wake();
+        assert!(woken.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn repeated_register_wake_cycles_do_not_leave_a_stale_completion_flag() {
+        let state = SharedState::new();
+
+        for _ in 0..3 {
+            let woken = AtomicBool::new(false);
+            state.register(&flag_waker(&woken));
+            assert!(
+                !woken.load(Ordering::SeqCst),
+                "register must not wake before the matching wake() of this cycle"
+            );
+
+            state.wake();
+            assert!(woken.load(Ordering::SeqCst));
+        }
+    }
+}